@@ -0,0 +1,361 @@
+use apollo_compiler::hir::{
+    EnumTypeDefinition, FieldDefinition, InputObjectTypeDefinition, ObjectTypeDefinition, Type,
+    TypeDefinition, UnionTypeDefinition,
+};
+use apollo_compiler::{ApolloCompiler, HirDatabase};
+use arbitrary::Unstructured;
+use std::io::prelude::*;
+use std::sync::Arc;
+
+use crate::{FragmentGeneratorError, FraggenResult};
+
+/// Hard cap on recursion depth so generation always terminates even when the
+/// byte buffer keeps asking to descend into self-referential types.
+const MAX_DEPTH: usize = 5;
+
+/// Number of attempts to build a schema-valid operation before giving up on a
+/// single slot. Random selections occasionally produce an invalid document
+/// (e.g. an empty selection set we could not repair); we simply retry.
+const MAX_ATTEMPTS: usize = 16;
+
+/// Generate up to `count` random but schema-valid operations and write them to
+/// `output`.
+///
+/// Each of the `count` slots gets at most [`MAX_ATTEMPTS`] candidates; a slot
+/// whose candidates all fail validation is skipped, so fewer than `count`
+/// operations may be written.
+///
+/// Generation is driven entirely from the `seed` byte buffer via
+/// [`arbitrary::Unstructured`], so a given seed reproduces the same corpus.
+/// Each candidate operation is validated against the compiler before it is
+/// emitted, so the output is always runnable against a conforming server.
+///
+/// # Errors
+/// Will return `Err` if the schema fails to parse or a referenced type cannot
+/// be resolved while walking the type system.
+pub fn fuzz<W: Write>(
+    schema_content: &str,
+    mut output: W,
+    count: usize,
+    seed: &[u8],
+    quiet: bool,
+) -> FraggenResult<()> {
+    let mut compiler = ApolloCompiler::new();
+    compiler.add_type_system(schema_content, "schema.graphql");
+
+    for diagnostic in compiler.validate() {
+        if diagnostic.data.is_error() {
+            return Err(FragmentGeneratorError::Parse(format!("{diagnostic}")));
+        }
+        if !quiet && (diagnostic.data.is_warning() || diagnostic.data.is_advice()) {
+            eprintln!("{diagnostic}");
+        }
+    }
+
+    let mut unstructured = Unstructured::new(seed);
+    let fuzzer = OperationFuzzer {
+        schema_content,
+        compiler,
+    };
+
+    let mut written = 0;
+    for index in 0..count {
+        for _ in 0..MAX_ATTEMPTS {
+            let operation = fuzzer.arbitrary_operation(&mut unstructured, index)?;
+            if fuzzer.is_valid(&operation) {
+                if written > 0 {
+                    writeln!(output)?;
+                }
+                write!(output, "{operation}")?;
+                written += 1;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct OperationFuzzer<'a> {
+    schema_content: &'a str,
+    compiler: ApolloCompiler,
+}
+
+impl OperationFuzzer<'_> {
+    /// Build a single random operation by picking a query-type root field and
+    /// recursively selecting into its result type.
+    fn arbitrary_operation(
+        &self,
+        u: &mut Unstructured,
+        index: usize,
+    ) -> FraggenResult<String> {
+        let schema = self.compiler.db.schema();
+        let query_name = schema
+            .query()
+            .ok_or(FragmentGeneratorError::Schema("schema has no query type"))?;
+
+        let type_system = self.compiler.db.type_system();
+        let query = match type_system.type_definitions_by_name.get(query_name) {
+            Some(TypeDefinition::ObjectTypeDefinition(typedef)) => typedef,
+            _ => return Err(FragmentGeneratorError::Schema("schema has no query type")),
+        };
+
+        let root_fields: Vec<&FieldDefinition> = query.fields().collect();
+        if root_fields.is_empty() {
+            return Err(FragmentGeneratorError::Schema("query type has no fields"));
+        }
+        let field = root_fields[self.choose_index(u, root_fields.len())?];
+
+        let mut body = String::new();
+        self.write_field(u, field, 1, &mut body)?;
+
+        Ok(format!("query FuzzOp{index} {{\n{body}}}\n"))
+    }
+
+    /// Validate a candidate operation against the schema; only valid documents
+    /// are emitted.
+    fn is_valid(&self, operation: &str) -> bool {
+        let mut compiler = ApolloCompiler::new();
+        compiler.add_type_system(self.schema_content, "schema.graphql");
+        compiler.add_executable(operation, "operation.graphql");
+        compiler.validate().iter().all(|diag| !diag.data.is_error())
+    }
+
+    fn write_field(
+        &self,
+        u: &mut Unstructured,
+        field: &FieldDefinition,
+        depth: usize,
+        output: &mut String,
+    ) -> FraggenResult<()> {
+        let indent = "  ".repeat(depth);
+        let arguments = self.arbitrary_arguments(u, field)?;
+
+        let mut base_type = field.ty();
+        while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = base_type {
+            base_type = ty;
+        }
+        let typedef = base_type
+            .type_def(&self.compiler.db)
+            .ok_or(FragmentGeneratorError::Schema("unresolved field type"))?;
+
+        match typedef {
+            TypeDefinition::ScalarTypeDefinition(_) | TypeDefinition::EnumTypeDefinition(_) => {
+                output.push_str(&format!("{indent}{}{arguments}\n", field.name()));
+            }
+            TypeDefinition::ObjectTypeDefinition(typedef) => {
+                let selection = self.arbitrary_object_selection(u, typedef.fields(), depth)?;
+                output.push_str(&format!("{indent}{}{arguments} {{\n", field.name()));
+                output.push_str(&selection);
+                output.push_str(&format!("{indent}}}\n"));
+            }
+            TypeDefinition::InterfaceTypeDefinition(typedef) => {
+                let selection = self.arbitrary_object_selection(u, typedef.fields(), depth)?;
+                output.push_str(&format!("{indent}{}{arguments} {{\n", field.name()));
+                output.push_str(&selection);
+                output.push_str(&format!("{indent}}}\n"));
+            }
+            TypeDefinition::UnionTypeDefinition(typedef) => {
+                let selection = self.arbitrary_union_selection(u, &typedef, depth)?;
+                output.push_str(&format!("{indent}{}{arguments} {{\n", field.name()));
+                output.push_str(&selection);
+                output.push_str(&format!("{indent}}}\n"));
+            }
+            TypeDefinition::InputObjectTypeDefinition(_) => {
+                return Err(FragmentGeneratorError::Schema("unsupported field type"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Select a subset of an object/interface's fields, recursing into complex
+    /// fields with a probability that decays with depth. Always selects at
+    /// least one field so the selection set is non-empty.
+    fn arbitrary_object_selection<'a>(
+        &self,
+        u: &mut Unstructured,
+        fields: impl Iterator<Item = &'a FieldDefinition>,
+        depth: usize,
+    ) -> FraggenResult<String> {
+        let fields: Vec<&FieldDefinition> = fields.collect();
+        let mut selection = String::new();
+
+        for field in &fields {
+            if self.include_field(u, field, depth)? {
+                self.write_field(u, field, depth + 1, &mut selection)?;
+            }
+        }
+
+        if selection.is_empty() {
+            let indent = "  ".repeat(depth + 1);
+            selection.push_str(&format!("{indent}__typename\n"));
+        }
+        Ok(selection)
+    }
+
+    fn arbitrary_union_selection(
+        &self,
+        u: &mut Unstructured,
+        typedef: &UnionTypeDefinition,
+        depth: usize,
+    ) -> FraggenResult<String> {
+        let members: Vec<Arc<ObjectTypeDefinition>> =
+            typedef.members().filter_map(|m| m.object(&self.compiler.db)).collect();
+        let indent = "  ".repeat(depth + 1);
+        let mut selection = String::new();
+
+        let mut picked = 0;
+        for member in &members {
+            if picked == 0 || u.arbitrary()? {
+                let fields = self.arbitrary_object_selection(u, member.fields(), depth + 1)?;
+                selection.push_str(&format!("{indent}... on {} {{\n", member.name()));
+                selection.push_str(&fields);
+                selection.push_str(&format!("{indent}}}\n"));
+                picked += 1;
+            }
+        }
+        Ok(selection)
+    }
+
+    fn arbitrary_arguments(
+        &self,
+        u: &mut Unstructured,
+        field: &FieldDefinition,
+    ) -> FraggenResult<String> {
+        let mut rendered = Vec::new();
+        for input_value in field.arguments().input_values() {
+            let required = matches!(input_value.ty(), Type::NonNull { .. })
+                && input_value.default_value().is_none();
+            if required || u.arbitrary()? {
+                let literal = self.arbitrary_literal(u, input_value.ty(), 0)?;
+                rendered.push(format!("{}: {literal}", input_value.name()));
+            }
+        }
+        if rendered.is_empty() {
+            Ok(String::new())
+        } else {
+            Ok(format!("({})", rendered.join(", ")))
+        }
+    }
+
+    /// Synthesize a literal for `ty`, recursing through non-null/list wrappers
+    /// and, for input objects, through their fields.
+    fn arbitrary_literal(
+        &self,
+        u: &mut Unstructured,
+        ty: &Type,
+        depth: usize,
+    ) -> FraggenResult<String> {
+        match ty {
+            Type::NonNull { ty, loc: _ } => self.arbitrary_literal(u, ty, depth),
+            Type::List { ty, loc: _ } => {
+                let element = self.arbitrary_literal(u, ty, depth)?;
+                Ok(format!("[{element}]"))
+            }
+            Type::Named { name, loc: _ } => {
+                let typedef = ty
+                    .type_def(&self.compiler.db)
+                    .ok_or(FragmentGeneratorError::Schema("unresolved argument type"))?;
+                match typedef {
+                    TypeDefinition::ScalarTypeDefinition(_) => self.arbitrary_scalar(u, name),
+                    TypeDefinition::EnumTypeDefinition(typedef) => self.arbitrary_enum(u, &typedef),
+                    TypeDefinition::InputObjectTypeDefinition(typedef) => {
+                        self.arbitrary_input_object(u, &typedef, depth)
+                    }
+                    _ => Err(FragmentGeneratorError::Schema("invalid argument type")),
+                }
+            }
+        }
+    }
+
+    fn arbitrary_scalar(&self, u: &mut Unstructured, name: &str) -> FraggenResult<String> {
+        let literal = match name {
+            "Int" => i32::from(u.arbitrary::<i16>()?).to_string(),
+            "Float" => format!("{}.0", u.arbitrary::<i16>()?),
+            "Boolean" => u.arbitrary::<bool>()?.to_string(),
+            // `String`, `ID` and any custom scalar are rendered as a string.
+            _ => format!("{:?}", self.arbitrary_string(u)?),
+        };
+        Ok(literal)
+    }
+
+    fn arbitrary_enum(
+        &self,
+        u: &mut Unstructured,
+        typedef: &EnumTypeDefinition,
+    ) -> FraggenResult<String> {
+        let values: Vec<&str> = typedef.values().map(|v| v.enum_value()).collect();
+        if values.is_empty() {
+            return Err(FragmentGeneratorError::Schema("enum has no values"));
+        }
+        Ok(values[self.choose_index(u, values.len())?].to_string())
+    }
+
+    fn arbitrary_input_object(
+        &self,
+        u: &mut Unstructured,
+        typedef: &InputObjectTypeDefinition,
+        depth: usize,
+    ) -> FraggenResult<String> {
+        if depth >= MAX_DEPTH {
+            return Ok("{}".to_string());
+        }
+        let mut fields = Vec::new();
+        for field in typedef.fields() {
+            let required = matches!(field.ty(), Type::NonNull { .. })
+                && field.default_value().is_none();
+            if required || u.arbitrary()? {
+                let literal = self.arbitrary_literal(u, field.ty(), depth + 1)?;
+                fields.push(format!("{}: {literal}", field.name()));
+            }
+        }
+        Ok(format!("{{{}}}", fields.join(", ")))
+    }
+
+    fn arbitrary_string(&self, u: &mut Unstructured) -> FraggenResult<String> {
+        let len = u.int_in_range(1..=8)?;
+        let mut string = String::with_capacity(len as usize);
+        for _ in 0..len {
+            let offset = u.int_in_range(0..=25)?;
+            string.push((b'a' + offset) as char);
+        }
+        Ok(string)
+    }
+
+    /// Decide whether to include `field`, biasing against deep recursion: the
+    /// deeper we are, the less likely a complex field is to be expanded.
+    fn include_field(
+        &self,
+        u: &mut Unstructured,
+        field: &FieldDefinition,
+        depth: usize,
+    ) -> FraggenResult<bool> {
+        let mut base_type = field.ty();
+        while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = base_type {
+            base_type = ty;
+        }
+        let complex = matches!(
+            base_type.type_def(&self.compiler.db),
+            Some(
+                TypeDefinition::ObjectTypeDefinition(_)
+                    | TypeDefinition::InterfaceTypeDefinition(_)
+                    | TypeDefinition::UnionTypeDefinition(_)
+            )
+        );
+
+        if complex {
+            if depth >= MAX_DEPTH {
+                return Ok(false);
+            }
+            // Probability of descending decays as ~1/(depth + 1).
+            Ok(u.int_in_range(0..=depth as u32)? == 0)
+        } else {
+            u.arbitrary().map_err(Into::into)
+        }
+    }
+
+    fn choose_index(&self, u: &mut Unstructured, len: usize) -> FraggenResult<usize> {
+        debug_assert!(len > 0);
+        Ok(u.int_in_range(0..=len - 1)?)
+    }
+}
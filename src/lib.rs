@@ -1,4 +1,4 @@
-use apollo_compiler::hir::{InputObjectTypeDefinition, Type, TypeDefinition};
+use apollo_compiler::hir::{InputObjectTypeDefinition, Type, TypeDefinition, Value};
 use apollo_compiler::{
     hir::{
         ArgumentsDefinition, FieldDefinition, ImplementsInterface, InputValueDefinition,
@@ -12,12 +12,16 @@ use thiserror::Error;
 /// # Errors
 /// Will return `Err` if there are errors parsing the schema, types can not be resolved
 /// or a field type is not supported.
+#[allow(clippy::too_many_arguments)]
 pub fn generate<W: Write>(
     schema_content: &str,
     output: W,
     prefix: &str,
     suffix: &str,
     add_typename: bool,
+    max_depth: usize,
+    operations: bool,
+    directives: DirectiveFilter<'_>,
     quiet: bool,
 ) -> FraggenResult<()> {
     let mut compiler = apollo_compiler::ApolloCompiler::new();
@@ -35,7 +39,28 @@ pub fn generate<W: Write>(
         }
     }
 
-    FragmentGenerator::new(compiler, output, prefix, suffix, add_typename).execute()
+    FragmentGenerator::new(
+        compiler,
+        output,
+        prefix,
+        suffix,
+        add_typename,
+        max_depth,
+        operations,
+        directives,
+    )
+    .execute()
+}
+
+/// Controls how field-level directives influence selection.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectiveFilter<'a> {
+    /// Omit fields carrying `@deprecated` entirely rather than commenting them.
+    pub skip_deprecated: bool,
+    /// When set, only select fields annotated with this directive.
+    pub include: Option<&'a str>,
+    /// When set, omit fields annotated with this directive.
+    pub exclude: Option<&'a str>,
 }
 
 #[derive(Error, Debug)]
@@ -51,9 +76,18 @@ pub enum FragmentGeneratorError {
 
     #[error("Schema error: {0}")]
     Schema(&'static str),
+
+    #[error("Arbitrary error: {0}")]
+    Arbitrary(#[from] arbitrary::Error),
 }
 
-type FraggenResult<T> = result::Result<T, FragmentGeneratorError>;
+pub(crate) type FraggenResult<T> = result::Result<T, FragmentGeneratorError>;
+
+mod fuzz;
+pub use fuzz::fuzz;
+
+#[cfg(test)]
+mod tests;
 
 struct FragmentGenerator<W: Write> {
     compiler: ApolloCompiler,
@@ -61,16 +95,25 @@ struct FragmentGenerator<W: Write> {
     prefix: String,
     suffix: String,
     add_typename: bool,
+    max_depth: usize,
+    operations: bool,
+    skip_deprecated: bool,
+    include_directive: Option<String>,
+    exclude_directive: Option<String>,
     write_newline: bool,
 }
 
 impl<W: Write> FragmentGenerator<W> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         compiler: ApolloCompiler,
         output: W,
         prefix: &str,
         suffix: &str,
         add_typename: bool,
+        max_depth: usize,
+        operations: bool,
+        directives: DirectiveFilter<'_>,
     ) -> Self {
         Self {
             compiler,
@@ -78,6 +121,11 @@ impl<W: Write> FragmentGenerator<W> {
             prefix: prefix.to_string(),
             suffix: suffix.to_string(),
             add_typename,
+            max_depth,
+            operations,
+            skip_deprecated: directives.skip_deprecated,
+            include_directive: directives.include.map(str::to_string),
+            exclude_directive: directives.exclude.map(str::to_string),
             write_newline: false,
         }
     }
@@ -88,7 +136,10 @@ impl<W: Write> FragmentGenerator<W> {
         for (_name, typedef) in type_system.type_definitions_by_name.iter() {
             match typedef {
                 TypeDefinition::ObjectTypeDefinition(typedef) if !typedef.is_introspection() => {
-                    self.write_object_fragment(typedef)?;
+                    match self.operations.then(|| self.operation_keyword(typedef.name())) {
+                        Some(Some(keyword)) => self.write_operation(typedef, keyword)?,
+                        _ => self.write_object_fragment(typedef)?,
+                    }
                 }
                 TypeDefinition::InterfaceTypeDefinition(typedef) => {
                     self.write_interface_fragment(typedef)?;
@@ -162,6 +213,7 @@ impl<W: Write> FragmentGenerator<W> {
         }
 
         let mut inherited_fields = HashSet::new();
+        let mut has_selection = add_typename;
 
         for implements_interface in implements_interfaces {
             let interface_typedef = implements_interface
@@ -171,18 +223,56 @@ impl<W: Write> FragmentGenerator<W> {
             let interface_name = implements_interface.interface();
             let fragment_name = self.fragment_name(interface_name);
             writeln!(self.output, "  ...{fragment_name}")?;
+            has_selection = true;
+        }
+
+        let mut path = HashSet::new();
+        path.insert(type_name.to_string());
+        let fields: Vec<&FieldDefinition> = fields
+            .filter(|fld| !inherited_fields.contains(fld.name()))
+            .collect();
+
+        // When every field is filtered out or only commented (e.g. all
+        // `@deprecated` in default mode, or excluded by directive filters) and
+        // the interface path adds no `__typename`, the body would be an invalid
+        // empty `{ }`. Emit `__typename` so the fragment keeps a valid selection.
+        if !has_selection {
+            for field in &fields {
+                if self.field_emits_selection(field, 0, &mut path)? {
+                    has_selection = true;
+                    break;
+                }
+            }
+        }
+        if !has_selection {
+            writeln!(self.output, "  __typename")?;
         }
 
-        for field in fields.filter(|fld| !inherited_fields.contains(fld.name())) {
-            self.write_field(field)?;
+        for field in fields {
+            self.write_field(field, 0, &mut path, "  ")?;
         }
 
         writeln!(self.output, "}}")?;
         Ok(())
     }
 
-    fn write_field(&mut self, field: &FieldDefinition) -> FraggenResult<()> {
+    fn write_field(
+        &mut self,
+        field: &FieldDefinition,
+        depth: usize,
+        path: &mut HashSet<String>,
+        indent: &str,
+    ) -> FraggenResult<()> {
         let field_name = field.name();
+
+        match self.field_action(field) {
+            FieldAction::Skip => return Ok(()),
+            FieldAction::Deprecated(reason) => {
+                return self.write_deprecated_comment(field_name, reason.as_deref(), indent);
+            }
+            FieldAction::Select => {}
+        }
+
         let mut field_type = field.ty();
 
         while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = field_type {
@@ -195,16 +285,39 @@ impl<W: Write> FragmentGenerator<W> {
 
         match field_type_definition {
             TypeDefinition::EnumTypeDefinition(_) | TypeDefinition::ScalarTypeDefinition(_) => {
-                self.write_simple_field(field_name, field.arguments())?;
+                self.write_simple_field(field_name, field.arguments(), indent)?;
             }
             TypeDefinition::ObjectTypeDefinition(typedef) => {
-                self.write_complex_field(field_name, typedef.name(), field.arguments())?;
+                self.write_complex_field(
+                    field_name,
+                    typedef.name(),
+                    typedef.fields(),
+                    field.arguments(),
+                    depth,
+                    path,
+                    indent,
+                )?;
             }
             TypeDefinition::InterfaceTypeDefinition(typedef) => {
-                self.write_complex_field(field_name, typedef.name(), field.arguments())?;
+                self.write_complex_field(
+                    field_name,
+                    typedef.name(),
+                    typedef.fields(),
+                    field.arguments(),
+                    depth,
+                    path,
+                    indent,
+                )?;
             }
             TypeDefinition::UnionTypeDefinition(typedef) => {
-                self.write_complex_field(field_name, typedef.name(), field.arguments())?;
+                // Unions carry no fields of their own, so there is nothing to
+                // inline directly; keep the spreadable placeholder form.
+                self.write_placeholder_field(
+                    field_name,
+                    typedef.name(),
+                    field.arguments(),
+                    indent,
+                )?;
             }
             TypeDefinition::InputObjectTypeDefinition(_) => {
                 Err(FragmentGeneratorError::Schema("unsupported field type"))?;
@@ -217,41 +330,332 @@ impl<W: Write> FragmentGenerator<W> {
         &mut self,
         field_name: &str,
         arguments: &ArgumentsDefinition,
+        indent: &str,
     ) -> FraggenResult<()> {
-        let arglist = self.format_arglist(arguments.input_values(), "  ")?;
-        writeln!(self.output, "  {field_name}{arglist}")?;
+        let arglist = self.format_arglist(arguments.input_values(), indent)?;
+        writeln!(self.output, "{indent}{field_name}{arglist}")?;
         Ok(())
     }
 
-    fn write_complex_field(
+    /// Emit a complex (object/interface) field.
+    ///
+    /// With `--max-depth`, nested selections are inlined directly: as long as
+    /// the base type is not already on the active path (which would form a
+    /// cycle, e.g. `Node.parent: Node`) and the depth budget is not exhausted,
+    /// the field's subfields are written out one level deeper. Otherwise the
+    /// field falls back to the commented-out placeholder so the output stays a
+    /// valid document.
+    #[allow(clippy::too_many_arguments)]
+    fn write_complex_field<'a>(
         &mut self,
         field_name: &str,
         type_name: &str,
+        subfields: impl Iterator<Item = &'a FieldDefinition>,
         arguments: &ArgumentsDefinition,
+        depth: usize,
+        path: &mut HashSet<String>,
+        indent: &str,
+    ) -> FraggenResult<()> {
+        // Inlining is only valid if at least one real subfield will be emitted;
+        // otherwise every child bottoms out at the depth cap or a cycle and we
+        // would produce an empty `{ }`, which the compiler rejects. Fall back to
+        // the commented-out placeholder in that case.
+        let subfields: Vec<&FieldDefinition> = subfields.collect();
+        if !self.complex_field_inlines(type_name, subfields.iter().copied(), depth, path)? {
+            return self.write_placeholder_field(field_name, type_name, arguments, indent);
+        }
+
+        let arglist = self.format_arglist(arguments.input_values(), indent)?;
+        writeln!(self.output, "{indent}{field_name}{arglist} {{")?;
+
+        let inner_indent = format!("{indent}  ");
+        path.insert(type_name.to_string());
+        for field in subfields {
+            self.write_field(field, depth + 1, path, &inner_indent)?;
+        }
+        path.remove(type_name);
+
+        writeln!(self.output, "{indent}}}")?;
+        Ok(())
+    }
+
+    /// Whether a complex field of `type_name` can be inlined at `depth`: the
+    /// depth budget must allow it, the type must not already be on the active
+    /// path (cycle), and at least one subfield must itself yield a real
+    /// selection so the resulting `{ ... }` is non-empty and valid.
+    fn complex_field_inlines<'a>(
+        &self,
+        type_name: &str,
+        subfields: impl Iterator<Item = &'a FieldDefinition>,
+        depth: usize,
+        path: &mut HashSet<String>,
+    ) -> FraggenResult<bool> {
+        if self.max_depth == 0 || depth >= self.max_depth || path.contains(type_name) {
+            return Ok(false);
+        }
+        path.insert(type_name.to_string());
+        let mut any = false;
+        for field in subfields {
+            if self.field_emits_selection(field, depth + 1, path)? {
+                any = true;
+                break;
+            }
+        }
+        path.remove(type_name);
+        Ok(any)
+    }
+
+    /// Dry-run of [`Self::write_field`]: whether writing this field at `depth`
+    /// would emit a real (non-comment) selection. A skipped or deprecated field
+    /// contributes only a comment, and a complex field that cannot be inlined
+    /// degrades to a commented placeholder, so neither keeps a selection set
+    /// non-empty.
+    fn field_emits_selection(
+        &self,
+        field: &FieldDefinition,
+        depth: usize,
+        path: &mut HashSet<String>,
+    ) -> FraggenResult<bool> {
+        if !matches!(self.field_action(field), FieldAction::Select) {
+            return Ok(false);
+        }
+
+        let mut field_type = field.ty();
+        while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = field_type {
+            field_type = ty;
+        }
+
+        let field_type_definition = field_type
+            .type_def(&self.compiler.db)
+            .ok_or(FragmentGeneratorError::Schema("unresolved field type"))?;
+
+        match field_type_definition {
+            TypeDefinition::EnumTypeDefinition(_) | TypeDefinition::ScalarTypeDefinition(_) => {
+                Ok(true)
+            }
+            TypeDefinition::ObjectTypeDefinition(typedef) => {
+                self.complex_field_inlines(typedef.name(), typedef.fields(), depth, path)
+            }
+            TypeDefinition::InterfaceTypeDefinition(typedef) => {
+                self.complex_field_inlines(typedef.name(), typedef.fields(), depth, path)
+            }
+            // Unions degrade to a commented placeholder and input objects are a
+            // schema error; neither contributes a real selection here.
+            TypeDefinition::UnionTypeDefinition(_)
+            | TypeDefinition::InputObjectTypeDefinition(_) => Ok(false),
+        }
+    }
+
+    fn write_placeholder_field(
+        &mut self,
+        field_name: &str,
+        type_name: &str,
+        arguments: &ArgumentsDefinition,
+        indent: &str,
     ) -> FraggenResult<()> {
         let fragment_name = self.fragment_name(type_name);
-        let arglist = self.format_arglist(arguments.input_values(), "  # ")?;
-        writeln!(self.output, "  # {field_name}{arglist} {{")?;
-        writeln!(self.output, "  #   ...{fragment_name}")?;
-        writeln!(self.output, "  # }}")?;
+        let comment_indent = format!("{indent}# ");
+        let arglist = self.format_arglist(arguments.input_values(), &comment_indent)?;
+        writeln!(self.output, "{indent}# {field_name}{arglist} {{")?;
+        writeln!(self.output, "{indent}#   ...{fragment_name}")?;
+        writeln!(self.output, "{indent}# }}")?;
+        Ok(())
+    }
+
+    /// Decide how a field should be treated given the configured directive
+    /// filters and its own `@deprecated` annotation.
+    fn field_action(&self, field: &FieldDefinition) -> FieldAction {
+        if let Some(include) = &self.include_directive {
+            if !field.directives().iter().any(|d| d.name() == include) {
+                return FieldAction::Skip;
+            }
+        }
+        if let Some(exclude) = &self.exclude_directive {
+            if field.directives().iter().any(|d| d.name() == exclude) {
+                return FieldAction::Skip;
+            }
+        }
+        if let Some(deprecated) = field.directive_by_name("deprecated") {
+            if self.skip_deprecated {
+                return FieldAction::Skip;
+            }
+            let reason = deprecated
+                .argument_by_name("reason")
+                .and_then(directive_string_argument);
+            return FieldAction::Deprecated(reason);
+        }
+        FieldAction::Select
+    }
+
+    /// Emit a deprecated field as a comment so consumers can see why it was
+    /// dropped while keeping the document valid.
+    fn write_deprecated_comment(
+        &mut self,
+        field_name: &str,
+        reason: Option<&str>,
+        indent: &str,
+    ) -> FraggenResult<()> {
+        match reason {
+            Some(reason) => writeln!(self.output, "{indent}# {field_name} (deprecated: {reason})")?,
+            None => writeln!(self.output, "{indent}# {field_name} (deprecated)")?,
+        }
+        Ok(())
+    }
+
+    /// Resolve `type_name` to its operation keyword if it is a root operation
+    /// type of the schema: one declared in its `schema { ... }` definition, or,
+    /// when there is none, a type named `Query`, `Mutation` or `Subscription`.
+    fn operation_keyword(&self, type_name: &str) -> Option<&'static str> {
+        let schema = self.compiler.db.schema();
+        if schema.query().is_some_and(|n| n == type_name) {
+            Some("query")
+        } else if schema.mutation().is_some_and(|n| n == type_name) {
+            Some("mutation")
+        } else if schema.subscription().is_some_and(|n| n == type_name) {
+            Some("subscription")
+        } else {
+            None
+        }
+    }
+
+    /// Emit a named, directly executable operation for a root type, complete
+    /// with a typed variable header collected from the selected field
+    /// arguments, e.g. `query MyQuery($name: String!, $top: Int) { ... }`.
+    fn write_operation(
+        &mut self,
+        typedef: &ObjectTypeDefinition,
+        keyword: &str,
+    ) -> FraggenResult<()> {
+        if self.write_newline {
+            writeln!(self.output)?;
+        } else {
+            self.write_newline = true;
+        }
+
+        let operation_name = self.fragment_name(typedef.name());
+
+        // Render the selection set first, letting the allocator accumulate the
+        // variable declarations (with collisions qualified) so the header can
+        // be printed ahead of a body that references exactly those names.
+        let mut allocator = VarAllocator::new();
+        let mut body = String::new();
+        if self.add_typename {
+            body.push_str("  __typename\n");
+        }
+        for field in typedef.fields() {
+            self.write_operation_field(field, "  ", &mut allocator, &mut body)?;
+        }
+
+        writeln!(
+            self.output,
+            "{keyword} {operation_name}{} {{",
+            allocator.header()
+        )?;
+        write!(self.output, "{body}")?;
+        writeln!(self.output, "}}")?;
+        Ok(())
+    }
+
+    /// Write a single field inside an operation selection set. Unlike fragment
+    /// bodies, complex fields always carry a real selection (a fragment spread)
+    /// so the resulting operation is valid on its own.
+    fn write_operation_field(
+        &self,
+        field: &FieldDefinition,
+        indent: &str,
+        allocator: &mut VarAllocator,
+        output: &mut String,
+    ) -> FraggenResult<()> {
+        let field_name = field.name();
+
+        match self.field_action(field) {
+            FieldAction::Skip => return Ok(()),
+            FieldAction::Deprecated(reason) => {
+                match reason {
+                    Some(reason) => {
+                        output.push_str(&format!("{indent}# {field_name} (deprecated: {reason})\n"));
+                    }
+                    None => output.push_str(&format!("{indent}# {field_name} (deprecated)\n")),
+                }
+                return Ok(());
+            }
+            FieldAction::Select => {}
+        }
+
+        let mut field_type = field.ty();
+        while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = field_type {
+            field_type = ty;
+        }
+
+        let field_type_definition = field_type
+            .type_def(&self.compiler.db)
+            .ok_or(FragmentGeneratorError::Schema("unresolved field type"))?;
+
+        let arglist = self.format_arglist_with(field.arguments().input_values(), indent, allocator)?;
+
+        match field_type_definition {
+            TypeDefinition::EnumTypeDefinition(_) | TypeDefinition::ScalarTypeDefinition(_) => {
+                output.push_str(&format!("{indent}{field_name}{arglist}\n"));
+            }
+            TypeDefinition::ObjectTypeDefinition(typedef) => {
+                self.write_operation_selection(field_name, typedef.name(), &arglist, indent, output);
+            }
+            TypeDefinition::InterfaceTypeDefinition(typedef) => {
+                self.write_operation_selection(field_name, typedef.name(), &arglist, indent, output);
+            }
+            TypeDefinition::UnionTypeDefinition(typedef) => {
+                self.write_operation_selection(field_name, typedef.name(), &arglist, indent, output);
+            }
+            TypeDefinition::InputObjectTypeDefinition(_) => {
+                Err(FragmentGeneratorError::Schema("unsupported field type"))?;
+            }
+        };
         Ok(())
     }
 
+    fn write_operation_selection(
+        &self,
+        field_name: &str,
+        type_name: &str,
+        arglist: &str,
+        indent: &str,
+        output: &mut String,
+    ) {
+        let fragment_name = self.fragment_name(type_name);
+        output.push_str(&format!("{indent}{field_name}{arglist} {{\n"));
+        output.push_str(&format!("{indent}  ...{fragment_name}\n"));
+        output.push_str(&format!("{indent}}}\n"));
+    }
+
     fn fragment_name(&self, type_name: &str) -> String {
         format!("{}{}{}", self.prefix, type_name, self.suffix)
     }
 
+    /// Format an argument list for a fragment body, where there is no operation
+    /// to declare variables against. Allocation is scoped to this single list.
     fn format_arglist(
         &self,
         input_values: &[InputValueDefinition],
         prefix: &str,
+    ) -> FraggenResult<String> {
+        self.format_arglist_with(input_values, prefix, &mut VarAllocator::new())
+    }
+
+    /// Format an argument list, threading `allocator` so that the variable
+    /// names chosen here match the operation header (and so that two arguments
+    /// or two nested input fields sharing a name do not collide silently).
+    fn format_arglist_with(
+        &self,
+        input_values: &[InputValueDefinition],
+        prefix: &str,
+        allocator: &mut VarAllocator,
     ) -> FraggenResult<String> {
         if input_values.is_empty() {
             Ok(String::new())
         } else {
             let args = input_values
                 .iter()
-                .map(|arg| self.format_arg(arg, prefix))
+                .map(|arg| self.format_arg(arg, prefix, allocator, &mut HashSet::new()))
                 .collect::<FraggenResult<Vec<String>>>()?;
             let join_str = format!("\n{prefix}  ");
             Ok(format!(" (\n{prefix}  {}\n{prefix})", args.join(&join_str)))
@@ -262,6 +666,8 @@ impl<W: Write> FragmentGenerator<W> {
         &self,
         input_value: &InputValueDefinition,
         prefix: &str,
+        allocator: &mut VarAllocator,
+        path: &mut HashSet<String>,
     ) -> FraggenResult<String> {
         let mut input_value_type = input_value.ty();
         while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = input_value_type {
@@ -274,31 +680,217 @@ impl<W: Write> FragmentGenerator<W> {
 
         match typedef {
             TypeDefinition::ScalarTypeDefinition(_) | TypeDefinition::EnumTypeDefinition(_) => {
-                Ok(format!("{0}: ${}", input_value.name()))
+                let var = allocator.allocate(
+                    input_value.name(),
+                    None,
+                    input_value.ty(),
+                    input_value.default_value(),
+                );
+                Ok(format!("{}: ${var}", input_value.name()))
+            }
+            TypeDefinition::InputObjectTypeDefinition(input_obj_typedef) => {
+                self.format_input_arg(input_value, &input_obj_typedef, prefix, allocator, path)
+            }
+            // Arguments must be input types, so an output type here is a schema
+            // error rather than something we can render.
+            TypeDefinition::ObjectTypeDefinition(_)
+            | TypeDefinition::InterfaceTypeDefinition(_)
+            | TypeDefinition::UnionTypeDefinition(_) => {
+                Err(FragmentGeneratorError::Schema("argument is not an input type"))
             }
-            TypeDefinition::InputObjectTypeDefinition(input_obj_typedef) => Ok(
-                Self::format_input_arg(input_value, &input_obj_typedef, prefix),
-            ),
-            TypeDefinition::ObjectTypeDefinition(_) => todo!(),
-            TypeDefinition::InterfaceTypeDefinition(_) => todo!(),
-            TypeDefinition::UnionTypeDefinition(_) => todo!(),
         }
     }
 
+    /// Render an input-object argument literal, recursing through nested input
+    /// objects one indentation level at a time. A path set keyed by input type
+    /// name guards against self-referential inputs, and the enclosing field
+    /// name is offered to the allocator as a qualifier when a leaf name clashes.
     fn format_input_arg(
+        &self,
         input_value_def: &InputValueDefinition,
         typedef: &InputObjectTypeDefinition,
         prefix: &str,
-    ) -> String {
+        allocator: &mut VarAllocator,
+        path: &mut HashSet<String>,
+    ) -> FraggenResult<String> {
+        let inner_prefix = format!("{prefix}  ");
+        path.insert(typedef.name().to_string());
+
+        let mut rendered = Vec::new();
+        for field in typedef.fields() {
+            let mut field_type = field.ty();
+            while let Type::NonNull { ty, loc: _ } | Type::List { ty, loc: _ } = field_type {
+                field_type = ty;
+            }
+            let field_typedef = field_type
+                .type_def(&self.compiler.db)
+                .ok_or(FragmentGeneratorError::Schema("unresolved argument type"))?;
+
+            match field_typedef {
+                TypeDefinition::InputObjectTypeDefinition(nested) => {
+                    // Skip the field entirely on a cycle so the literal stays
+                    // finite and valid.
+                    if path.contains(nested.name()) {
+                        continue;
+                    }
+                    rendered.push(
+                        self.format_input_arg(field, &nested, &inner_prefix, allocator, path)?,
+                    );
+                }
+                _ => {
+                    let var = allocator.allocate(
+                        field.name(),
+                        Some(input_value_def.name()),
+                        field.ty(),
+                        field.default_value(),
+                    );
+                    rendered.push(format!("{}: ${var}", field.name()));
+                }
+            }
+        }
+
+        path.remove(typedef.name());
+
         let join_str = format!("\n{prefix}    ");
-        let args = typedef
-            .fields()
-            .map(|field| format!("{0}: ${0}", field.name()))
-            .collect::<Vec<String>>()
-            .join(&join_str);
-        format!(
+        let args = rendered.join(&join_str);
+        Ok(format!(
             "{0}: {{\n{prefix}    {args}\n{prefix}  }}",
             input_value_def.name()
-        )
+        ))
+    }
+}
+
+/// Allocates unique GraphQL variable names and records their declarations in
+/// document order.
+///
+/// Variable names are derived from the innermost field name, so two arguments
+/// (or two nested input fields) with the same leaf name would otherwise collide
+/// silently. On a clash the allocator qualifies the name — first with the
+/// enclosing field as a prefix (`$beer_name`), then with a numeric suffix —
+/// threading the chosen name through both the argument literal and the matching
+/// operation-header declaration.
+struct VarAllocator {
+    used: HashSet<String>,
+    declarations: Vec<(String, String, Option<String>)>,
+}
+
+impl VarAllocator {
+    fn new() -> Self {
+        Self {
+            used: HashSet::new(),
+            declarations: Vec::new(),
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        base: &str,
+        qualifier: Option<&str>,
+        ty: &Type,
+        default_value: Option<&Value>,
+    ) -> String {
+        let name = self.unique_name(base, qualifier);
+        self.used.insert(name.clone());
+        self.declarations.push((
+            name.clone(),
+            render_type(ty),
+            default_value.map(render_value),
+        ));
+        name
+    }
+
+    fn unique_name(&self, base: &str, qualifier: Option<&str>) -> String {
+        if !self.used.contains(base) {
+            return base.to_string();
+        }
+        if let Some(qualifier) = qualifier {
+            let qualified = format!("{qualifier}_{base}");
+            if !self.used.contains(&qualified) {
+                return qualified;
+            }
+        }
+        let mut counter = 2;
+        loop {
+            let candidate = format!("{base}{counter}");
+            if !self.used.contains(&candidate) {
+                return candidate;
+            }
+            counter += 1;
+        }
+    }
+
+    /// Render the `($var: Type = default, ...)` operation header, or the empty
+    /// string when no variables were allocated.
+    fn header(&self) -> String {
+        if self.declarations.is_empty() {
+            return String::new();
+        }
+        let declarations = self
+            .declarations
+            .iter()
+            .map(|(name, ty, default)| match default {
+                Some(default) => format!("${name}: {ty} = {default}"),
+                None => format!("${name}: {ty}"),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("({declarations})")
+    }
+}
+
+/// What to do with a field once its directives have been considered.
+enum FieldAction {
+    /// Select the field normally.
+    Select,
+    /// Omit the field entirely (directive filter or `--skip-deprecated`).
+    Skip,
+    /// Emit the field as a comment, carrying the deprecation reason if any.
+    Deprecated(Option<String>),
+}
+
+/// Extract the string payload of a directive argument (e.g. the `reason` of
+/// `@deprecated(reason: "...")`).
+fn directive_string_argument(value: &Value) -> Option<String> {
+    match value {
+        Value::String { value, loc: _ } => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Render a type back to its SDL spelling, preserving the full non-null/list
+/// wrapping (`String!`, `[ID!]!`) needed for variable declarations.
+fn render_type(ty: &Type) -> String {
+    match ty {
+        Type::NonNull { ty, loc: _ } => format!("{}!", render_type(ty)),
+        Type::List { ty, loc: _ } => format!("[{}]", render_type(ty)),
+        Type::Named { name, loc: _ } => name.clone(),
+    }
+}
+
+/// Render a literal value back to its SDL spelling, used for variable default
+/// values taken from an [`InputValueDefinition`].
+fn render_value(value: &Value) -> String {
+    match value {
+        Value::Variable(variable) => format!("${}", variable.name()),
+        Value::Int { value, loc: _ } => value
+            .to_i32_checked()
+            .map_or_else(|| value.get().to_string(), |int| int.to_string()),
+        Value::Float { value, loc: _ } => value.get().to_string(),
+        Value::String { value, loc: _ } => format!("{value:?}"),
+        Value::Boolean { value, loc: _ } => value.to_string(),
+        Value::Null { loc: _ } => "null".to_string(),
+        Value::Enum { value, loc: _ } => value.src().to_string(),
+        Value::List { value, loc: _ } => {
+            let items = value.iter().map(render_value).collect::<Vec<_>>().join(", ");
+            format!("[{items}]")
+        }
+        Value::Object { value, loc: _ } => {
+            let fields = value
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name.src(), render_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{{{fields}}}")
+        }
     }
 }
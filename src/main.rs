@@ -25,6 +25,39 @@ struct Cli {
     #[arg(long)]
     typename: bool,
 
+    /// Inline nested selections up to this depth instead of emitting
+    /// commented-out placeholders for object/interface fields; union fields
+    /// always keep the placeholder
+    #[arg(long, default_value_t = 0)]
+    max_depth: usize,
+
+    /// Emit executable operations (with typed variable headers) for the
+    /// schema's root operation types instead of bare fragments
+    #[arg(long)]
+    operations: bool,
+
+    /// Instead of fragments, emit up to this many random schema-valid
+    /// operations for fuzzing or snapshot corpora (0 disables)
+    #[arg(long, default_value_t = 0)]
+    fuzz: usize,
+
+    /// Seed string driving random operation generation; the same seed
+    /// reproduces the same corpus
+    #[arg(long, default_value = "")]
+    seed: String,
+
+    /// Omit fields carrying @deprecated instead of emitting them as a comment
+    #[arg(long)]
+    skip_deprecated: bool,
+
+    /// Only select fields annotated with this directive
+    #[arg(long)]
+    include_directive: Option<String>,
+
+    /// Omit fields annotated with this directive
+    #[arg(long)]
+    exclude_directive: Option<String>,
+
     /// Don't print warnings or advice
     #[arg(short, long)]
     quiet: bool,
@@ -37,14 +70,31 @@ fn main() -> Result<()> {
     let schema_content = std::fs::read_to_string(args.schema)?;
 
     let output = BufWriter::new(stdout().lock());
-    fraggen::generate(
-        &schema_content,
-        output,
-        &args.prefix,
-        &args.suffix,
-        args.typename,
-        args.quiet,
-    )?;
+    if args.fuzz > 0 {
+        fraggen::fuzz(
+            &schema_content,
+            output,
+            args.fuzz,
+            args.seed.as_bytes(),
+            args.quiet,
+        )?;
+    } else {
+        fraggen::generate(
+            &schema_content,
+            output,
+            &args.prefix,
+            &args.suffix,
+            args.typename,
+            args.max_depth,
+            args.operations,
+            fraggen::DirectiveFilter {
+                skip_deprecated: args.skip_deprecated,
+                include: args.include_directive.as_deref(),
+                exclude: args.exclude_directive.as_deref(),
+            },
+            args.quiet,
+        )?;
+    }
 
     Ok(())
 }
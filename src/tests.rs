@@ -16,6 +16,22 @@ fn assert_valid(output: &str) {
     }
 }
 
+/// Validate generated executable output (operations) against its schema, which
+/// must be present for the document to resolve its root operation type.
+fn assert_valid_operation(schema: &str, output: &str) {
+    let mut compiler = ApolloCompiler::new();
+    compiler.add_type_system(schema, "schema.graphql");
+    compiler.add_executable(output, "generated.graphql");
+    for diag in compiler.validate() {
+        if let DiagnosticData::UnusedFragment { name: _ } = *diag.data {
+            continue;
+        }
+        eprintln!("{diag}");
+        eprintln!("{diag:?}");
+        assert!(!diag.data.is_error());
+    }
+}
+
 #[test]
 fn test_single_level() {
     let schema = indoc! {"
@@ -39,7 +55,7 @@ fn test_single_level() {
     "};
 
     let mut output = Vec::new();
-    crate::generate(&schema, &mut output, "My", "Fields", true, true).unwrap();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
     let fragments = from_utf8(&output).unwrap();
 
     assert_valid(fragments);
@@ -72,7 +88,114 @@ fn test_multilevel() {
     "};
 
     let mut output = Vec::new();
-    crate::generate(&schema, &mut output, "My", "Fields", true, true).unwrap();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_max_depth_inlines_nested() {
+    let schema = indoc! {"
+        type Foo {
+          bar: Bar
+        }
+
+        type Bar {
+          baz: Int
+        }
+    "};
+    let expected = indoc! {"
+        fragment MyFooFields on Foo {
+          __typename
+          bar {
+            baz
+          }
+        }
+
+        fragment MyBarFields on Bar {
+          __typename
+          baz
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 1, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_max_depth_stops_at_cycle() {
+    let schema = indoc! {"
+        type Node {
+          id: ID
+          parent: Node
+        }
+    "};
+    let expected = indoc! {"
+        fragment MyNodeFields on Node {
+          __typename
+          id
+          # parent {
+          #   ...MyNodeFields
+          # }
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 10, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_max_depth_falls_back_when_subfields_truncated() {
+    // `bar` can be inlined (depth 0 < 1), but its only field `qux` hits the
+    // depth cap (depth 1 >= 1) and would become a comment. An inlined `bar`
+    // would then contain only a comment, i.e. an invalid empty selection, so
+    // the whole field must fall back to the commented-out placeholder instead.
+    let schema = indoc! {"
+        type Foo {
+          bar: Bar
+        }
+
+        type Bar {
+          qux: Baz
+        }
+
+        type Baz {
+          id: ID
+        }
+    "};
+    let expected = indoc! {"
+        fragment MyFooFields on Foo {
+          __typename
+          # bar {
+          #   ...MyBarFields
+          # }
+        }
+
+        fragment MyBarFields on Bar {
+          __typename
+          qux {
+            id
+          }
+        }
+
+        fragment MyBazFields on Baz {
+          __typename
+          id
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 1, false, crate::DirectiveFilter::default(), true).unwrap();
     let fragments = from_utf8(&output).unwrap();
 
     assert_valid(fragments);
@@ -107,7 +230,7 @@ fn test_implements_interface() {
     "};
 
     let mut output = Vec::new();
-    crate::generate(&schema, &mut output, "My", "Fields", true, true).unwrap();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
     let fragments = from_utf8(&output).unwrap();
 
     assert_valid(fragments);
@@ -132,13 +255,85 @@ fn test_arguments() {
     "};
 
     let mut output = Vec::new();
-    crate::generate(&schema, &mut output, "My", "Fields", true, true).unwrap();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
     let fragments = from_utf8(&output).unwrap();
 
     assert_valid(fragments);
     assert_eq!(expected, fragments);
 }
 
+#[test]
+fn test_operations() {
+    let schema = indoc! {"
+        type SearchResult {
+          id: ID
+        }
+
+        type Query {
+          searchBeer(name: String!, top: Int): SearchResult
+        }
+    "};
+    let expected = indoc! {"
+        fragment MySearchResultFields on SearchResult {
+          __typename
+          id
+        }
+
+        query MyQueryFields($name: String!, $top: Int) {
+          __typename
+          searchBeer (
+            name: $name
+            top: $top
+          ) {
+            ...MySearchResultFields
+          }
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, true, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid_operation(schema, fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_fuzz_is_reproducible_and_valid() {
+    let schema = indoc! {"
+        type Beer {
+          id: ID
+          name: String
+          abv: Float
+        }
+
+        type Query {
+          searchBeer(name: String!, top: Int): [Beer]
+        }
+    "};
+
+    let seed = b"fraggen-seed-0123456789abcdef";
+
+    let mut first = Vec::new();
+    crate::fuzz(schema, &mut first, 3, seed, true).unwrap();
+    let first = from_utf8(&first).unwrap().to_string();
+
+    let mut second = Vec::new();
+    crate::fuzz(schema, &mut second, 3, seed, true).unwrap();
+    let second = from_utf8(&second).unwrap();
+
+    // The same seed reproduces the same corpus.
+    assert_eq!(first, second);
+
+    // Every generated operation validates against the schema.
+    let mut compiler = ApolloCompiler::new();
+    compiler.add_type_system(schema, "schema.graphql");
+    compiler.add_executable(&first, "operations.graphql");
+    for diag in compiler.validate() {
+        assert!(!diag.data.is_error(), "{diag}");
+    }
+}
+
 #[test]
 fn test_input() {
     let schema = indoc! {"
@@ -172,7 +367,193 @@ fn test_input() {
     "};
 
     let mut output = Vec::new();
-    crate::generate(&schema, &mut output, "My", "Fields", true, true).unwrap();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_nested_input() {
+    let schema = indoc! {"
+        input Location {
+          city: String
+          country: String
+        }
+
+        input BeerInput {
+          name: String
+          origin: Location
+        }
+
+        type Mutation {
+          postBeer(beer: BeerInput): ID
+        }
+    "};
+    let expected = indoc! {"
+        fragment MyMutationFields on Mutation {
+          __typename
+          postBeer (
+            beer: {
+              name: $name
+              origin: {
+                city: $city
+                country: $country
+              }
+            }
+          )
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_deduplicates_variable_names() {
+    let schema = indoc! {"
+        input BeerInput {
+          name: String
+        }
+
+        type Mutation {
+          postBeer(name: String, beer: BeerInput): ID
+        }
+    "};
+    let expected = indoc! {"
+        mutation MyMutationFields($name: String, $beer_name: String) {
+          __typename
+          postBeer (
+            name: $name
+            beer: {
+              name: $beer_name
+            }
+          )
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, true, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid_operation(schema, fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_deprecated_field_commented() {
+    let schema = indoc! {r#"
+        type Foo {
+          id: ID
+          old: String @deprecated(reason: "use id")
+        }
+    "#};
+    let expected = indoc! {"
+        fragment MyFooFields on Foo {
+          __typename
+          id
+          # old (deprecated: use id)
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_skip_deprecated() {
+    let schema = indoc! {r#"
+        type Foo {
+          id: ID
+          old: String @deprecated(reason: "use id")
+        }
+    "#};
+    let expected = indoc! {"
+        fragment MyFooFields on Foo {
+          __typename
+          id
+        }
+    "};
+
+    let directives = crate::DirectiveFilter {
+        skip_deprecated: true,
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, directives, true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_include_directive() {
+    let schema = indoc! {"
+        directive @public on FIELD_DEFINITION
+
+        type Foo {
+          public_field: String @public
+          internal_field: String
+        }
+    "};
+    let expected = indoc! {"
+        fragment MyFooFields on Foo {
+          __typename
+          public_field
+        }
+    "};
+
+    let directives = crate::DirectiveFilter {
+        include: Some("public"),
+        ..Default::default()
+    };
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, directives, true).unwrap();
+    let fragments = from_utf8(&output).unwrap();
+
+    assert_valid(fragments);
+    assert_eq!(expected, fragments);
+}
+
+#[test]
+fn test_interface_with_only_commented_fields_keeps_typename() {
+    // An interface fragment carries no `__typename` of its own, so an interface
+    // whose every field is commented out (here, all `@deprecated` in the default
+    // mode) would leave an invalid empty selection set. `__typename` must be
+    // injected to keep the fragment valid.
+    let schema = indoc! {r#"
+        interface Node {
+          old: String @deprecated(reason: "gone")
+        }
+
+        type Thing implements Node {
+          old: String @deprecated(reason: "gone")
+        }
+    "#};
+    let expected = indoc! {"
+        fragment MyThingFields on Thing {
+          __typename
+          ...MyNodeFields
+        }
+
+        fragment MyNodeFields on Node {
+          __typename
+          # old (deprecated: gone)
+        }
+    "};
+
+    let mut output = Vec::new();
+    crate::generate(schema, &mut output, "My", "Fields", true, 0, false, crate::DirectiveFilter::default(), true).unwrap();
     let fragments = from_utf8(&output).unwrap();
 
     assert_valid(fragments);